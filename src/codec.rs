@@ -0,0 +1,301 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use mhash::MultiHash;
+
+use multiaddr::{M, S};
+use {MultiAddr, Segment};
+
+/// Errors that can occur while decoding a `MultiAddr` from its canonical
+/// binary wire format.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The protocol code read from the buffer did not match the segment
+    /// being decoded.
+    UnknownProtocol(u64),
+    /// The buffer ended before a complete segment could be read. Also
+    /// returned when a length-prefixed value (a string that is not valid
+    /// UTF-8, or bytes that are not a valid multihash) could not be decoded.
+    Truncated,
+    /// A varint was longer than 9 bytes (63 bits of value).
+    OverlongVarint,
+    /// The buffer had bytes left over after the `MultiAddr` was decoded.
+    TrailingData,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnknownProtocol(code) => write!(f, "unexpected protocol code {}", code),
+            DecodeError::Truncated => write!(f, "buffer ended before a complete segment"),
+            DecodeError::OverlongVarint => write!(f, "varint longer than 9 bytes"),
+            DecodeError::TrailingData => write!(f, "trailing data after decoded multiaddr"),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn description(&self) -> &str {
+        match *self {
+            DecodeError::UnknownProtocol(_) => "unexpected protocol code",
+            DecodeError::Truncated => "buffer ended before a complete segment",
+            DecodeError::OverlongVarint => "varint longer than 9 bytes",
+            DecodeError::TrailingData => "trailing data after decoded multiaddr",
+        }
+    }
+}
+
+/// Write `value` to `out` as an unsigned LEB128 varint: 7 bits per byte,
+/// low byte first, with the high bit set on every byte but the last.
+///
+/// `value` must be less than 2^63: matching [`read_varint`](fn.read_varint.html)'s
+/// 9-byte limit, which only covers that range. No protocol code or
+/// length prefix in this format ever approaches that size.
+pub fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        } else {
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+/// Read an unsigned LEB128 varint from the front of `input`, advancing it
+/// past the bytes consumed. Errors if the buffer runs out first, or if the
+/// varint is longer than 9 bytes, so values of 2^63 and above are rejected.
+pub fn read_varint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value: u64 = 0;
+    for i in 0..9 {
+        if input.is_empty() {
+            return Err(DecodeError::Truncated);
+        }
+        let byte = input[0];
+        *input = &input[1..];
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::OverlongVarint)
+}
+
+/// A type whose binary representation can appear as a segment's value.
+pub trait Codable: Sized {
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+impl Codable for u16 {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push((*self >> 8) as u8);
+        out.push(*self as u8);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<u16, DecodeError> {
+        if input.len() < 2 {
+            return Err(DecodeError::Truncated);
+        }
+        let value = ((input[0] as u16) << 8) | (input[1] as u16);
+        *input = &input[2..];
+        Ok(value)
+    }
+}
+
+impl Codable for Ipv4Addr {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.octets());
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Ipv4Addr, DecodeError> {
+        if input.len() < 4 {
+            return Err(DecodeError::Truncated);
+        }
+        let addr = Ipv4Addr::new(input[0], input[1], input[2], input[3]);
+        *input = &input[4..];
+        Ok(addr)
+    }
+}
+
+impl Codable for Ipv6Addr {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.octets());
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Ipv6Addr, DecodeError> {
+        if input.len() < 16 {
+            return Err(DecodeError::Truncated);
+        }
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(&input[..16]);
+        *input = &input[16..];
+        Ok(Ipv6Addr::from(octets))
+    }
+}
+
+impl Codable for String {
+    fn write(&self, out: &mut Vec<u8>) {
+        let bytes = self.as_bytes();
+        write_varint(bytes.len() as u64, out);
+        out.extend_from_slice(bytes);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<String, DecodeError> {
+        let len = try!(read_varint(input)) as usize;
+        if input.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        let (bytes, rest) = input.split_at(len);
+        let s = try!(String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::Truncated));
+        *input = rest;
+        Ok(s)
+    }
+}
+
+impl Codable for MultiHash {
+    fn write(&self, out: &mut Vec<u8>) {
+        let bytes = self.as_bytes();
+        write_varint(bytes.len() as u64, out);
+        out.extend_from_slice(bytes);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<MultiHash, DecodeError> {
+        let len = try!(read_varint(input)) as usize;
+        if input.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        let (bytes, rest) = input.split_at(len);
+        let hash = try!(MultiHash::from_bytes(bytes).map_err(|_| DecodeError::Truncated));
+        *input = rest;
+        Ok(hash)
+    }
+}
+
+/// Encode a `MultiAddr` to the canonical multiaddr binary wire format: each
+/// segment as a varint protocol code followed by its value.
+pub trait WriteMultiAddrExt: MultiAddr {
+    #[doc(hidden)]
+    fn write_bytes(&self, out: &mut Vec<u8>);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_bytes(&mut out);
+        out
+    }
+}
+
+/// Decode a `MultiAddr` from the canonical multiaddr binary wire format.
+pub trait ReadMultiAddrExt: MultiAddr {
+    #[doc(hidden)]
+    fn read_from(input: &mut &[u8]) -> Result<Self, DecodeError>;
+
+    fn from_bytes(input: &[u8]) -> Result<Self, DecodeError> {
+        let mut cursor = input;
+        let addr = try!(Self::read_from(&mut cursor));
+        if !cursor.is_empty() {
+            return Err(DecodeError::TrailingData);
+        }
+        Ok(addr)
+    }
+}
+
+impl<T> WriteMultiAddrExt for S<T> where T: Segment {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        write_varint(T::code(), out);
+        self.0.write_value(out);
+    }
+}
+
+impl<T> ReadMultiAddrExt for S<T> where T: Segment {
+    fn read_from(input: &mut &[u8]) -> Result<S<T>, DecodeError> {
+        let code = try!(read_varint(input));
+        if code != T::code() {
+            return Err(DecodeError::UnknownProtocol(code));
+        }
+        Ok(S(try!(T::read_value(input))))
+    }
+}
+
+impl<T, U> WriteMultiAddrExt for M<T, U> where T: MultiAddr + WriteMultiAddrExt, U: Segment {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        self.0.write_bytes(out);
+        S(self.1.clone()).write_bytes(out);
+    }
+}
+
+impl<T, U> ReadMultiAddrExt for M<T, U> where T: MultiAddr + ReadMultiAddrExt, U: Segment {
+    fn read_from(input: &mut &[u8]) -> Result<M<T, U>, DecodeError> {
+        let prefix = try!(T::read_from(input));
+        let S(segment) = try!(S::<U>::read_from(input));
+        Ok(M(prefix, segment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use multiaddr::{M, S};
+    use segment::{Tcp, IP4};
+
+    use super::{read_varint, write_varint, DecodeError, ReadMultiAddrExt, WriteMultiAddrExt};
+
+    #[test]
+    fn varint_round_trips() {
+        // 9 bytes of 7 bits each cover values up to 2^63 - 1; that's the
+        // largest value `write_varint` may produce without tripping the
+        // 9-byte overlong check on the read side.
+        for &value in &[0u64, 1, 127, 128, 300, (1u64 << 63) - 1] {
+            let mut bytes = Vec::new();
+            write_varint(value, &mut bytes);
+            let mut cursor = &bytes[..];
+            assert_eq!(read_varint(&mut cursor).unwrap(), value);
+            assert!(cursor.is_empty());
+        }
+    }
+
+    #[test]
+    fn read_varint_overlong() {
+        // 9 continuation bytes and a final byte: a 10-byte varint, one past
+        // the 9-byte limit.
+        let bytes = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        let mut cursor = &bytes[..];
+        match read_varint(&mut cursor) {
+            Err(DecodeError::OverlongVarint) => {}
+            other => panic!("expected OverlongVarint, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn read_varint_truncated() {
+        let bytes = [0x80, 0x80];
+        let mut cursor = &bytes[..];
+        match read_varint(&mut cursor) {
+            Err(DecodeError::Truncated) => {}
+            other => panic!("expected Truncated, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn multiaddr_round_trips() {
+        let addr = M(S(IP4 { ip: Ipv4Addr::new(1, 2, 3, 4) }), Tcp { port: 80 });
+        let bytes = addr.to_bytes();
+        let decoded = M::<S<IP4>, Tcp>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, addr);
+    }
+
+    #[test]
+    fn trailing_data_errors() {
+        let addr = S(IP4 { ip: Ipv4Addr::new(1, 2, 3, 4) });
+        let mut bytes = addr.to_bytes();
+        bytes.push(0);
+        match S::<IP4>::from_bytes(&bytes) {
+            Err(DecodeError::TrailingData) => {}
+            other => panic!("expected TrailingData, got {:?}", other),
+        }
+    }
+}
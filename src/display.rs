@@ -5,23 +5,23 @@ use multiaddr::{S, M};
 
 #[cfg(test)]
 mod tests {
-    use std::net::{ Ipv4Addr, Ipv6Addr };
+    use std::net::{Ipv4Addr, Ipv6Addr};
 
-    use mhash::{ MultiHash, MultiHashVariant };
+    use mhash::{MultiHash, MultiHashVariant};
 
-    use segment::{IP4, IP6, Ipfs};
-    use { MultiAddr, Segment };
+    use multiaddr::{M, S};
+    use segment::{Ipfs, IP4, IP6};
 
     #[test]
     fn ip4() {
         let addr = Ipv4Addr::new(1, 2, 3, 4);
-        assert_eq!(IP4(addr).to_string(), "/ip4/1.2.3.4");
+        assert_eq!(S(IP4::from(addr)).to_string(), "/ip4/1.2.3.4");
     }
 
     #[test]
     fn ip6() {
         let addr = Ipv6Addr::new(0x2a02, 0x6b8, 0, 0, 0, 0, 0x11, 0x11);
-        assert_eq!(IP6(addr).to_string(), "/ip6/2a02:6b8::11:11");
+        assert_eq!(S(IP6::from(addr)).to_string(), "/ip6/2a02:6b8::11:11");
     }
 
     #[test]
@@ -33,7 +33,7 @@ mod tests {
             194, 13, 183, 106, 104, 145, 28, 11,
         ]).unwrap();
         assert_eq!(
-            Ipfs(multihash).to_string(),
+            S(Ipfs::new(multihash)).to_string(),
             "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
     }
 
@@ -47,7 +47,7 @@ mod tests {
             194, 13, 183, 106, 104, 145, 28, 11,
         ]).unwrap();
         assert_eq!(
-            M(Ip4(addr), Ipfs(multihash)).to_string(),
-            "/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
+            M(S(IP4::from(addr)), Ipfs::new(multihash)).to_string(),
+            "/ip4/1.2.3.4/ipfs/QmcgpsyWgH8Y8ajJz1Cu72KnS5uo2Aa2LpzU7kinSupNKC");
     }
 }
@@ -0,0 +1,24 @@
+//! `maddr` is a strongly typed implementation of [multiaddr][], the
+//! self-describing network address format used by IPFS/libp2p.
+//!
+//! [multiaddr]: https://github.com/multiformats/multiaddr
+
+extern crate mhash;
+
+mod codec;
+mod display;
+mod multiaddr;
+mod net;
+mod onion;
+mod parse;
+mod segment;
+
+pub use codec::{DecodeError, ReadMultiAddrExt, WriteMultiAddrExt};
+pub use multiaddr::{AnySegment, MultiAddr, SegmentsExt, SplitAt, SplitSegments, M, S};
+pub use net::{SocketMultiAddr, ToSocketAddrExt, UrlAddr, UrlError};
+pub use parse::ParseError;
+pub use onion::InvalidIdLength;
+pub use segment::{
+    Dccp, Dns, Dns4, Dns6, Dnsaddr, Http, Https, Ipfs, Onion, Onion3, P2pCircuit, Quic, Sctp,
+    Segment, Tcp, Udp, Udt, Unix, Utp, Ws, Wss, IP4, IP6,
+};
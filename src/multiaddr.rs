@@ -1,27 +1,27 @@
 use std::fmt;
-use std::ops::Add;
 
 use Segment;
 
-/// A decoded multiaddr.
+/// A decoded multiaddr: either a single [`S`](struct.S.html) segment, or a
+/// chain of segments built up with [`M`](struct.M.html).
 ///
 /// # Examples
 ///
-/// This type can be converted from some standard library types via
-/// `From<T> where Segment: From<T>`, e.g. from `Ipv4Addr`:
+/// A single segment, built from a type with a `From` impl, e.g. `IP4` from
+/// `Ipv4Addr`:
 ///
 /// ```rust
 /// use std::net::Ipv4Addr;
-/// use maddr::{Segment, MultiAddr};
+/// use maddr::{S, IP4};
 ///
 /// let addr = Ipv4Addr::new(1, 2, 3, 4);
-/// let multiaddr = addr.into();
+/// let multiaddr = S(IP4::from(addr));
 ///
-/// assert_eq!(MultiAddr::new(vec![Segment::IP4(addr)]), multiaddr);
+/// assert_eq!("/ip4/1.2.3.4", multiaddr.to_string());
 /// ```
 ///
-/// check the [segment trait implementations to see what types those
-/// are](enum.Segment.html#implementations).
+/// check the [`Segment`](trait.Segment.html#implementors) implementors for
+/// the full list of segment types.
 ///
 /// ---
 ///
@@ -31,21 +31,120 @@ use Segment;
 ///
 /// ```rust
 /// use std::net::Ipv4Addr;
-/// use maddr::{Segment, MultiAddr};
+/// use maddr::{M, S, Tcp, IP4};
 ///
 /// let addr = Ipv4Addr::new(1, 2, 3, 4);
-/// let multiaddr = Segment::from(addr) + Segment::Tcp(22);
+/// let multiaddr = M(S(IP4::from(addr)), Tcp::new(22));
 ///
 /// assert_eq!("/ip4/1.2.3.4/tcp/22", multiaddr.to_string());
 /// ```
 pub trait MultiAddr: fmt::Display + Eq + PartialEq + Clone {
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct S<T: Segment>(T);
+#[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+pub struct S<T: Segment>(pub(crate) T);
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct M<T: MultiAddr, U: Segment>(T, U);
+#[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+pub struct M<T: MultiAddr, U: Segment>(pub(crate) T, pub(crate) U);
+
+impl<T: MultiAddr, U: Segment> M<T, U> {
+    /// Split this multiaddr into its prefix (everything before the last
+    /// segment) and its last segment.
+    ///
+    /// This is the building block for splitting a multiaddr at a protocol
+    /// boundary, e.g. taking the transport prefix up to and including
+    /// `tcp` and keeping the remainder: for `addr: M<M<S<IP4>, Tcp>, Http>`,
+    /// `addr.split()` gives back `(M<S<IP4>, Tcp>, Http)`, the `tcp`-ending
+    /// prefix and the `http` remainder.
+    pub fn split(self) -> (T, U) {
+        (self.0, self.1)
+    }
+
+    /// The prefix of this multiaddr, i.e. everything before the last
+    /// segment.
+    pub fn prefix(&self) -> &T {
+        &self.0
+    }
+
+    /// The last segment of this multiaddr.
+    pub fn last(&self) -> &U {
+        &self.1
+    }
+}
+
+/// An object-safe view onto a single segment of a `MultiAddr`, for when
+/// the concrete `Segment` type can't be named (e.g. the segments making up
+/// a runtime-determined chain).
+pub trait AnySegment {
+    /// This segment's protocol code.
+    fn code(&self) -> u64;
+    /// This segment's protocol name.
+    fn name(&self) -> &'static str;
+    /// This segment rendered as its own `/name/value` chunk.
+    fn render(&self) -> String;
+}
+
+impl<T: Segment> AnySegment for T {
+    fn code(&self) -> u64 { T::code() }
+    fn name(&self) -> &'static str { T::name() }
+    fn render(&self) -> String {
+        S(self.clone()).to_string()
+    }
+}
+
+/// Iterate over the constituent segments of a `MultiAddr`.
+pub trait SegmentsExt: MultiAddr {
+    fn segments(&self) -> Vec<Box<AnySegment>>;
+}
+
+impl<T> SegmentsExt for S<T> where T: Segment {
+    fn segments(&self) -> Vec<Box<AnySegment>> {
+        vec![Box::new(self.0.clone())]
+    }
+}
+
+impl<T, U> SegmentsExt for M<T, U> where T: MultiAddr + SegmentsExt, U: Segment {
+    fn segments(&self) -> Vec<Box<AnySegment>> {
+        let mut segments = self.0.segments();
+        segments.push(Box::new(self.1.clone()));
+        segments
+    }
+}
+
+/// The two halves of a multiaddr's segments produced by
+/// [`SplitAt::split_at`](trait.SplitAt.html#method.split_at): everything up
+/// to and including the split point, and everything after it.
+pub type SplitSegments = (Vec<Box<AnySegment>>, Vec<Box<AnySegment>>);
+
+/// Search a multiaddr's segments for a protocol boundary and split there.
+///
+/// Unlike [`M::split`](struct.M.html#method.split), which only ever peels
+/// off the last segment, this searches the whole chain (from the start)
+/// for the first occurrence of protocol `P`, wherever it falls.
+pub trait SplitAt: MultiAddr + SegmentsExt {
+    /// Split this multiaddr's segments at the first occurrence of `P`,
+    /// searching from the start: everything up to and including the `P`
+    /// segment, and everything after it. For example, splitting
+    /// `/ip4/1.2.3.4/tcp/80/http` at `Tcp` gives back the `ip4`/`tcp`
+    /// segments and the remaining `http` segment.
+    ///
+    /// Returns `None` if no segment with protocol `P` is present.
+    ///
+    /// The split can't be expressed as a concrete `MultiAddr`, since its
+    /// shape depends on where the split point falls at runtime, so both
+    /// halves are returned as `AnySegment`s instead.
+    fn split_at<P: Segment>(&self) -> Option<SplitSegments> {
+        let mut segments = self.segments();
+        let pos = segments.iter().position(|s| s.code() == P::code());
+        pos.map(move |i| {
+            let after = segments.split_off(i + 1);
+            (segments, after)
+        })
+    }
+}
+
+impl<T: MultiAddr + SegmentsExt> SplitAt for T {
+}
 
 impl<T> MultiAddr for S<T> where T: Segment {
 }
@@ -75,22 +174,19 @@ impl<T, U> fmt::Display for M<T, U> where T: MultiAddr, U: Segment {
 mod tests {
     use std::net::Ipv4Addr;
 
-    use {MultiAddr, Segment};
+    use segment::{Tcp, IP4};
+    use multiaddr::{M, S};
 
     #[test]
-    fn from_ip4() {
-        assert_eq!(
-            MultiAddr::new(vec![Segment::IP4(Ipv4Addr::new(1, 2, 3, 4))]),
-            Ipv4Addr::new(1, 2, 3, 4).into());
+    fn single_segment_displays() {
+        let addr = Ipv4Addr::new(1, 2, 3, 4);
+        assert_eq!(S(IP4::from(addr)).to_string(), "/ip4/1.2.3.4");
     }
 
     #[test]
-    fn add() {
-        assert_eq!(
-            MultiAddr::new(vec![
-                Segment::IP4(Ipv4Addr::new(1, 2, 3, 4)),
-                Segment::Tcp(22),
-            ]),
-            MultiAddr::from(Ipv4Addr::new(1, 2, 3, 4)) + Segment::Tcp(22));
+    fn chained_segments_display() {
+        let addr = Ipv4Addr::new(1, 2, 3, 4);
+        let multiaddr = M(S(IP4::from(addr)), Tcp::new(22));
+        assert_eq!(multiaddr.to_string(), "/ip4/1.2.3.4/tcp/22");
     }
 }
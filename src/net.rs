@@ -0,0 +1,332 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use multiaddr::{M, S};
+use segment::{Dns4, Http, Https, Tcp, Udp, Unix, Ws, Wss, IP4, IP6};
+use MultiAddr;
+
+/// Errors that can occur while building a `MultiAddr` from a URL.
+#[derive(Debug)]
+pub enum UrlError {
+    /// The URL did not use one of the supported schemes.
+    UnsupportedScheme(String),
+    /// The URL had no host component.
+    MissingHost,
+    /// The URL's port component was not a valid port number.
+    InvalidPort(String),
+    /// A `unix://` URL had no path component.
+    MissingPath,
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UrlError::UnsupportedScheme(ref scheme) => write!(f, "unsupported URL scheme `{}`", scheme),
+            UrlError::MissingHost => write!(f, "URL has no host"),
+            UrlError::InvalidPort(ref port) => write!(f, "invalid port `{}`", port),
+            UrlError::MissingPath => write!(f, "URL has no path"),
+        }
+    }
+}
+
+impl Error for UrlError {
+    fn description(&self) -> &str {
+        match *self {
+            UrlError::UnsupportedScheme(_) => "unsupported URL scheme",
+            UrlError::MissingHost => "URL has no host",
+            UrlError::InvalidPort(_) => "invalid port",
+            UrlError::MissingPath => "URL has no path",
+        }
+    }
+}
+
+/// A `MultiAddr` built from a URL. Which segment chain is produced depends
+/// on the URL's scheme and on whether its host is a literal IPv4 address or
+/// a hostname.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum UrlAddr {
+    Http(M<M<S<IP4>, Tcp>, Http>),
+    HttpDns(M<M<S<Dns4>, Tcp>, Http>),
+    Https(M<M<S<IP4>, Tcp>, Https>),
+    HttpsDns(M<M<S<Dns4>, Tcp>, Https>),
+    Ws(M<M<S<IP4>, Tcp>, Ws>),
+    WsDns(M<M<S<Dns4>, Tcp>, Ws>),
+    Wss(M<M<S<IP4>, Tcp>, Wss>),
+    WssDns(M<M<S<Dns4>, Tcp>, Wss>),
+    Unix(S<Unix>),
+}
+
+impl fmt::Display for UrlAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UrlAddr::Http(ref addr) => addr.fmt(f),
+            UrlAddr::HttpDns(ref addr) => addr.fmt(f),
+            UrlAddr::Https(ref addr) => addr.fmt(f),
+            UrlAddr::HttpsDns(ref addr) => addr.fmt(f),
+            UrlAddr::Ws(ref addr) => addr.fmt(f),
+            UrlAddr::WsDns(ref addr) => addr.fmt(f),
+            UrlAddr::Wss(ref addr) => addr.fmt(f),
+            UrlAddr::WssDns(ref addr) => addr.fmt(f),
+            UrlAddr::Unix(ref addr) => addr.fmt(f),
+        }
+    }
+}
+
+impl MultiAddr for UrlAddr {
+}
+
+impl UrlAddr {
+    /// Parse a URL into a `MultiAddr`. Supports the `http`, `https`, `ws`,
+    /// `wss`, and `unix` schemes; for the network schemes a literal IPv4
+    /// host produces an `ip4` prefix, otherwise a `dns4` prefix is used,
+    /// and the port defaults from the scheme when not given.
+    pub fn from_url(url: &str) -> Result<UrlAddr, UrlError> {
+        let scheme_end = try!(url.find("://").ok_or_else(|| UrlError::UnsupportedScheme(url.to_owned())));
+        let scheme = &url[..scheme_end];
+        let rest = &url[scheme_end + 3..];
+
+        if scheme == "unix" {
+            if rest.is_empty() {
+                return Err(UrlError::MissingPath);
+            }
+            return Ok(UrlAddr::Unix(S(Unix::new(rest.to_owned()))));
+        }
+
+        let default_port = match scheme {
+            "http" | "ws" => 80,
+            "https" | "wss" => 443,
+            _ => return Err(UrlError::UnsupportedScheme(scheme.to_owned())),
+        };
+
+        let host_port = match rest.find('/') {
+            Some(i) => &rest[..i],
+            None => rest,
+        };
+        let (host, port) = match host_port.rfind(':') {
+            Some(i) => {
+                let port = try!(host_port[i + 1..].parse()
+                    .map_err(|_| UrlError::InvalidPort(host_port[i + 1..].to_owned())));
+                (&host_port[..i], port)
+            }
+            None => (host_port, default_port),
+        };
+        if host.is_empty() {
+            return Err(UrlError::MissingHost);
+        }
+
+        let tcp = Tcp { port: port };
+        let ip4 = host.parse::<Ipv4Addr>().ok();
+        Ok(match (scheme, ip4) {
+            ("http", Some(ip)) => UrlAddr::Http(M(M(S(IP4 { ip: ip }), tcp), Http)),
+            ("http", None) => UrlAddr::HttpDns(M(M(S(Dns4 { host: host.to_owned() }), tcp), Http)),
+            ("https", Some(ip)) => UrlAddr::Https(M(M(S(IP4 { ip: ip }), tcp), Https)),
+            ("https", None) => UrlAddr::HttpsDns(M(M(S(Dns4 { host: host.to_owned() }), tcp), Https)),
+            ("ws", Some(ip)) => UrlAddr::Ws(M(M(S(IP4 { ip: ip }), tcp), Ws)),
+            ("ws", None) => UrlAddr::WsDns(M(M(S(Dns4 { host: host.to_owned() }), tcp), Ws)),
+            ("wss", Some(ip)) => UrlAddr::Wss(M(M(S(IP4 { ip: ip }), tcp), Wss)),
+            ("wss", None) => UrlAddr::WssDns(M(M(S(Dns4 { host: host.to_owned() }), tcp), Wss)),
+            _ => unreachable!(),
+        })
+    }
+}
+
+impl From<SocketAddrV4> for M<S<IP4>, Tcp> {
+    fn from(addr: SocketAddrV4) -> M<S<IP4>, Tcp> {
+        M(S(IP4 { ip: *addr.ip() }), Tcp { port: addr.port() })
+    }
+}
+
+impl From<SocketAddrV6> for M<S<IP6>, Tcp> {
+    fn from(addr: SocketAddrV6) -> M<S<IP6>, Tcp> {
+        M(S(IP6 { ip: *addr.ip() }), Tcp { port: addr.port() })
+    }
+}
+
+/// A `MultiAddr` built from a `SocketAddr`: an `ip4`/`tcp` chain for a
+/// `V4` address, or an `ip6`/`tcp` chain for a `V6` one.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum SocketMultiAddr {
+    V4(M<S<IP4>, Tcp>),
+    V6(M<S<IP6>, Tcp>),
+}
+
+impl fmt::Display for SocketMultiAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SocketMultiAddr::V4(ref addr) => addr.fmt(f),
+            SocketMultiAddr::V6(ref addr) => addr.fmt(f),
+        }
+    }
+}
+
+impl MultiAddr for SocketMultiAddr {
+}
+
+impl From<SocketAddr> for SocketMultiAddr {
+    fn from(addr: SocketAddr) -> SocketMultiAddr {
+        match addr {
+            SocketAddr::V4(addr) => SocketMultiAddr::V4(addr.into()),
+            SocketAddr::V6(addr) => SocketMultiAddr::V6(addr.into()),
+        }
+    }
+}
+
+/// Extract a `SocketAddr` from a `MultiAddr` whose prefix is a concrete
+/// `ip4`/`tcp`, `ip4`/`udp`, `ip6`/`tcp`, or `ip6`/`udp` chain. The
+/// `ip4`/`tcp` and `ip6`/`tcp` chains are also supported with one trailing
+/// `http`, `https`, `ws`, or `wss` segment, as produced by
+/// [`UrlAddr::from_url`]; no other trailing segment is supported, and the
+/// `udp` chains are supported only without one.
+pub trait ToSocketAddrExt {
+    fn to_socket_addr(&self) -> SocketAddr;
+}
+
+impl ToSocketAddrExt for M<S<IP4>, Tcp> {
+    fn to_socket_addr(&self) -> SocketAddr {
+        SocketAddr::V4(SocketAddrV4::new((self.0).0.ip, self.1.port))
+    }
+}
+
+impl ToSocketAddrExt for M<S<IP4>, Udp> {
+    fn to_socket_addr(&self) -> SocketAddr {
+        SocketAddr::V4(SocketAddrV4::new((self.0).0.ip, self.1.port))
+    }
+}
+
+impl ToSocketAddrExt for M<S<IP6>, Tcp> {
+    fn to_socket_addr(&self) -> SocketAddr {
+        SocketAddr::V6(SocketAddrV6::new((self.0).0.ip, self.1.port, 0, 0))
+    }
+}
+
+impl ToSocketAddrExt for M<S<IP6>, Udp> {
+    fn to_socket_addr(&self) -> SocketAddr {
+        SocketAddr::V6(SocketAddrV6::new((self.0).0.ip, self.1.port, 0, 0))
+    }
+}
+
+impl ToSocketAddrExt for SocketMultiAddr {
+    fn to_socket_addr(&self) -> SocketAddr {
+        match *self {
+            SocketMultiAddr::V4(ref addr) => addr.to_socket_addr(),
+            SocketMultiAddr::V6(ref addr) => addr.to_socket_addr(),
+        }
+    }
+}
+
+/// Implement `ToSocketAddrExt` for an `ip4`/`tcp` or `ip6`/`tcp` chain with
+/// one extra trailing segment, by delegating to the `ip4`/`ip6` prefix.
+macro_rules! to_socket_addr_with_trailer {
+    ($trailer:ty) => {
+        impl ToSocketAddrExt for M<M<S<IP4>, Tcp>, $trailer> {
+            fn to_socket_addr(&self) -> SocketAddr {
+                self.prefix().to_socket_addr()
+            }
+        }
+
+        impl ToSocketAddrExt for M<M<S<IP6>, Tcp>, $trailer> {
+            fn to_socket_addr(&self) -> SocketAddr {
+                self.prefix().to_socket_addr()
+            }
+        }
+    };
+}
+
+to_socket_addr_with_trailer!(Http);
+to_socket_addr_with_trailer!(Https);
+to_socket_addr_with_trailer!(Ws);
+to_socket_addr_with_trailer!(Wss);
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+    use super::{SocketMultiAddr, ToSocketAddrExt, UrlAddr, UrlError};
+
+    #[test]
+    fn http_with_ip_host() {
+        let addr = UrlAddr::from_url("http://1.2.3.4:8080/foo").unwrap();
+        assert_eq!(addr.to_string(), "/ip4/1.2.3.4/tcp/8080/http");
+    }
+
+    #[test]
+    fn http_with_dns_host_and_default_port() {
+        let addr = UrlAddr::from_url("http://example.com/foo").unwrap();
+        assert_eq!(addr.to_string(), "/dns4/example.com/tcp/80/http");
+    }
+
+    #[test]
+    fn https_with_dns_host() {
+        let addr = UrlAddr::from_url("https://example.com/foo").unwrap();
+        assert_eq!(addr.to_string(), "/dns4/example.com/tcp/443/https");
+    }
+
+    #[test]
+    fn ws_with_ip_host() {
+        let addr = UrlAddr::from_url("ws://1.2.3.4:1234/").unwrap();
+        assert_eq!(addr.to_string(), "/ip4/1.2.3.4/tcp/1234/ws");
+    }
+
+    #[test]
+    fn wss_with_dns_host_and_default_port() {
+        let addr = UrlAddr::from_url("wss://example.com/").unwrap();
+        assert_eq!(addr.to_string(), "/dns4/example.com/tcp/443/wss");
+    }
+
+    #[test]
+    fn unix_scheme() {
+        let addr = UrlAddr::from_url("unix:///tmp/foo.sock").unwrap();
+        assert_eq!(addr.to_string(), "/unix/tmp/foo.sock");
+    }
+
+    #[test]
+    fn unix_scheme_missing_path_errors() {
+        match UrlAddr::from_url("unix://") {
+            Err(UrlError::MissingPath) => {}
+            other => panic!("expected MissingPath, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unsupported_scheme_errors() {
+        match UrlAddr::from_url("ftp://example.com/") {
+            Err(UrlError::UnsupportedScheme(ref scheme)) if scheme == "ftp" => {}
+            other => panic!("expected UnsupportedScheme, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn socket_addr_v4_round_trips() {
+        let socket = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(1, 2, 3, 4), 80));
+        let addr: SocketMultiAddr = socket.into();
+        assert_eq!(addr.to_string(), "/ip4/1.2.3.4/tcp/80");
+        assert_eq!(addr.to_socket_addr(), socket);
+    }
+
+    #[test]
+    fn socket_addr_v6_round_trips() {
+        let socket = SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::new(0x2a02, 0x6b8, 0, 0, 0, 0, 0x11, 0x11), 80, 0, 0));
+        let addr: SocketMultiAddr = socket.into();
+        assert_eq!(addr.to_string(), "/ip6/2a02:6b8::11:11/tcp/80");
+        assert_eq!(addr.to_socket_addr(), socket);
+    }
+
+    #[test]
+    fn url_addr_with_ip_host_extracts_socket_addr() {
+        let socket = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(1, 2, 3, 4), 8080));
+        match UrlAddr::from_url("http://1.2.3.4:8080/foo").unwrap() {
+            UrlAddr::Http(addr) => assert_eq!(addr.to_socket_addr(), socket),
+            other => panic!("expected UrlAddr::Http, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wss_url_addr_with_ip_host_extracts_socket_addr() {
+        let socket = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(1, 2, 3, 4), 443));
+        match UrlAddr::from_url("wss://1.2.3.4/").unwrap() {
+            UrlAddr::Wss(addr) => assert_eq!(addr.to_socket_addr(), socket),
+            other => panic!("expected UrlAddr::Wss, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::error::Error;
+use std::fmt;
+
+use codec::{Codable, DecodeError};
+use parse::{Parsable, ParseError};
+
+/// The service id passed to [`Onion::new`](../struct.Onion.html) or
+/// [`Onion3::new`](../struct.Onion3.html) was not the length that protocol
+/// requires.
+#[derive(Debug)]
+pub struct InvalidIdLength {
+    pub(crate) expected: usize,
+    pub(crate) found: usize,
+}
+
+impl fmt::Display for InvalidIdLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a {}-byte service id, got {} bytes", self.expected, self.found)
+    }
+}
+
+impl Error for InvalidIdLength {
+    fn description(&self) -> &str {
+        "invalid service id length"
+    }
+}
+
+const ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn decode(s: &str) -> Result<Vec<u8>, ()> {
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    for c in s.chars() {
+        let lower = c.to_ascii_lowercase();
+        let value = try!(ALPHABET.iter().position(|&b| b as char == lower).ok_or(()));
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push(((buffer >> bits) & 0xff) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn parse_address(name: &'static str, s: &str, id_len: usize) -> Result<(Vec<u8>, u16), ParseError> {
+    let colon = match s.rfind(':') {
+        Some(i) => i,
+        None => return Err(ParseError::InvalidValue(name, s.to_owned())),
+    };
+    let id = try!(decode(&s[..colon]).map_err(|_| ParseError::InvalidValue(name, s.to_owned())));
+    if id.len() != id_len {
+        return Err(ParseError::InvalidValue(name, s.to_owned()));
+    }
+    let port = try!(<u16 as Parsable>::parse(name, &s[colon + 1..]));
+    Ok((id, port))
+}
+
+/// The id + port of a Tor version 2 hidden service (80-bit id).
+#[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+pub(crate) struct OnionV2Address {
+    pub(crate) id: Vec<u8>,
+    pub(crate) port: u16,
+}
+
+impl fmt::Display for OnionV2Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", encode(&self.id), self.port)
+    }
+}
+
+impl Codable for OnionV2Address {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.id);
+        Codable::write(&self.port, out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<OnionV2Address, DecodeError> {
+        if input.len() < 10 {
+            return Err(DecodeError::Truncated);
+        }
+        let (id, rest) = input.split_at(10);
+        let id = id.to_vec();
+        *input = rest;
+        Ok(OnionV2Address { id: id, port: try!(Codable::read(input)) })
+    }
+}
+
+impl Parsable for OnionV2Address {
+    fn parse(name: &'static str, s: &str) -> Result<OnionV2Address, ParseError> {
+        let (id, port) = try!(parse_address(name, s, 10));
+        Ok(OnionV2Address { id: id, port: port })
+    }
+}
+
+/// The id + port of a Tor version 3 hidden service (35-byte id).
+#[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+pub(crate) struct OnionV3Address {
+    pub(crate) id: Vec<u8>,
+    pub(crate) port: u16,
+}
+
+impl fmt::Display for OnionV3Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", encode(&self.id), self.port)
+    }
+}
+
+impl Codable for OnionV3Address {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.id);
+        Codable::write(&self.port, out);
+    }
+
+    fn read(input: &mut &[u8]) -> Result<OnionV3Address, DecodeError> {
+        if input.len() < 35 {
+            return Err(DecodeError::Truncated);
+        }
+        let (id, rest) = input.split_at(35);
+        let id = id.to_vec();
+        *input = rest;
+        Ok(OnionV3Address { id: id, port: try!(Codable::read(input)) })
+    }
+}
+
+impl Parsable for OnionV3Address {
+    fn parse(name: &'static str, s: &str) -> Result<OnionV3Address, ParseError> {
+        let (id, port) = try!(parse_address(name, s, 35));
+        Ok(OnionV3Address { id: id, port: port })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, OnionV2Address};
+    use codec::Codable;
+    use parse::Parsable;
+
+    #[test]
+    fn base32_round_trips() {
+        let id = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        assert_eq!(decode(&encode(&id)).unwrap(), id);
+    }
+
+    #[test]
+    fn onion_display() {
+        let addr = OnionV2Address { id: vec![0; 10], port: 80 };
+        assert_eq!(addr.to_string(), "aaaaaaaaaaaaaaaa:80");
+    }
+
+    #[test]
+    fn onion_parse_round_trips() {
+        let addr = OnionV2Address { id: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10], port: 443 };
+        let rendered = addr.to_string();
+        let parsed = <OnionV2Address as Parsable>::parse("onion", &rendered).unwrap();
+        assert_eq!(parsed, addr);
+    }
+
+    #[test]
+    fn onion_codec_round_trips() {
+        let addr = OnionV2Address { id: vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10], port: 443 };
+        let mut bytes = Vec::new();
+        addr.write(&mut bytes);
+        let mut cursor = &bytes[..];
+        let decoded = OnionV2Address::read(&mut cursor).unwrap();
+        assert_eq!(decoded, addr);
+        assert!(cursor.is_empty());
+    }
+}
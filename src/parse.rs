@@ -0,0 +1,227 @@
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use mhash::MultiHash;
+
+use multiaddr::{M, S};
+use {MultiAddr, Segment};
+
+/// Errors that can occur while parsing a `MultiAddr` from its
+/// human-readable `/proto/value/...` form.
+#[derive(Debug)]
+pub enum ParseError {
+    /// A component did not name the protocol expected at that position.
+    UnknownProtocol(String),
+    /// A protocol's argument, or the protocol component itself, was missing
+    /// from the input.
+    MissingArgument(&'static str),
+    /// A protocol's argument could not be parsed.
+    InvalidValue(&'static str, String),
+    /// The input had components left over after the `MultiAddr` was parsed.
+    TrailingComponents,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::UnknownProtocol(ref name) => write!(f, "unexpected protocol `{}`", name),
+            ParseError::MissingArgument(name) => write!(f, "missing argument for `{}`", name),
+            ParseError::InvalidValue(name, ref value) =>
+                write!(f, "invalid value `{}` for `{}`", value, name),
+            ParseError::TrailingComponents => write!(f, "trailing components after multiaddr"),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        match *self {
+            ParseError::UnknownProtocol(_) => "unexpected protocol",
+            ParseError::MissingArgument(_) => "missing argument",
+            ParseError::InvalidValue(..) => "invalid value",
+            ParseError::TrailingComponents => "trailing components after multiaddr",
+        }
+    }
+}
+
+/// A type that can be parsed from a single component of the human-readable
+/// multiaddr form.
+pub trait Parsable: Sized {
+    fn parse(name: &'static str, s: &str) -> Result<Self, ParseError>;
+}
+
+impl Parsable for u16 {
+    fn parse(name: &'static str, s: &str) -> Result<u16, ParseError> {
+        s.parse().map_err(|_| ParseError::InvalidValue(name, s.to_owned()))
+    }
+}
+
+impl Parsable for Ipv4Addr {
+    fn parse(name: &'static str, s: &str) -> Result<Ipv4Addr, ParseError> {
+        s.parse().map_err(|_| ParseError::InvalidValue(name, s.to_owned()))
+    }
+}
+
+impl Parsable for Ipv6Addr {
+    fn parse(name: &'static str, s: &str) -> Result<Ipv6Addr, ParseError> {
+        s.parse().map_err(|_| ParseError::InvalidValue(name, s.to_owned()))
+    }
+}
+
+impl Parsable for String {
+    fn parse(_name: &'static str, s: &str) -> Result<String, ParseError> {
+        Ok(s.to_owned())
+    }
+}
+
+impl Parsable for MultiHash {
+    fn parse(name: &'static str, s: &str) -> Result<MultiHash, ParseError> {
+        MultiHash::from_base58(s).map_err(|_| ParseError::InvalidValue(name, s.to_owned()))
+    }
+}
+
+/// Implemented by every `MultiAddr` chain type: parse this chain's
+/// components off the front of `components`.
+pub trait FromComponents: MultiAddr {
+    fn from_components<'a, I: Iterator<Item=&'a str>>(components: &mut I) -> Result<Self, ParseError>;
+}
+
+impl<T> FromComponents for S<T> where T: Segment {
+    fn from_components<'a, I: Iterator<Item=&'a str>>(components: &mut I) -> Result<S<T>, ParseError> {
+        let name = try!(components.next().ok_or(ParseError::MissingArgument(T::name())));
+        if name != T::name() {
+            return Err(ParseError::UnknownProtocol(name.to_owned()));
+        }
+        Ok(S(try!(T::parse_args(components))))
+    }
+}
+
+impl<T, U> FromComponents for M<T, U> where T: MultiAddr + FromComponents, U: Segment {
+    fn from_components<'a, I: Iterator<Item=&'a str>>(components: &mut I) -> Result<M<T, U>, ParseError> {
+        let prefix = try!(T::from_components(components));
+        let S(segment) = try!(S::<U>::from_components(components));
+        Ok(M(prefix, segment))
+    }
+}
+
+fn parse_multiaddr<A: FromComponents>(s: &str) -> Result<A, ParseError> {
+    let mut components = s.split('/');
+    match components.next() {
+        Some("") | None => {}
+        Some(other) => return Err(ParseError::UnknownProtocol(other.to_owned())),
+    }
+    let addr = try!(A::from_components(&mut components));
+    if components.next().is_some() {
+        return Err(ParseError::TrailingComponents);
+    }
+    Ok(addr)
+}
+
+impl<T> FromStr for S<T> where T: Segment {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<S<T>, ParseError> {
+        parse_multiaddr(s)
+    }
+}
+
+impl<T, U> FromStr for M<T, U> where T: MultiAddr + FromComponents, U: Segment {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<M<T, U>, ParseError> {
+        parse_multiaddr(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use multiaddr::{M, S};
+    use segment::{Dns, Dns6, Dnsaddr, Tcp, Unix, IP4};
+
+    use super::ParseError;
+
+    #[test]
+    fn multiaddr_round_trips() {
+        let addr = M(S(IP4 { ip: Ipv4Addr::new(1, 2, 3, 4) }), Tcp { port: 80 });
+        let rendered = addr.to_string();
+        assert_eq!(rendered, "/ip4/1.2.3.4/tcp/80");
+        assert!(rendered.parse::<M<S<IP4>, Tcp>>().unwrap() == addr);
+    }
+
+    #[test]
+    fn unknown_protocol_errors() {
+        match "/tcp/80".parse::<S<IP4>>() {
+            Err(ParseError::UnknownProtocol(ref name)) if name == "tcp" => {}
+            Err(_) => panic!("expected UnknownProtocol"),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn missing_argument_errors() {
+        match "/ip4".parse::<S<IP4>>() {
+            Err(ParseError::MissingArgument("ip4")) => {}
+            Err(_) => panic!("expected MissingArgument"),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn invalid_value_errors() {
+        match "/ip4/not-an-ip".parse::<S<IP4>>() {
+            Err(ParseError::InvalidValue("ip4", ref value)) if value == "not-an-ip" => {}
+            Err(_) => panic!("expected InvalidValue"),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn trailing_components_error() {
+        match "/ip4/1.2.3.4/tcp/80".parse::<S<IP4>>() {
+            Err(ParseError::TrailingComponents) => {}
+            Err(_) => panic!("expected TrailingComponents"),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn unix_round_trips() {
+        let addr = S(Unix::new("/tmp/foo.sock".to_owned()));
+        let rendered = addr.to_string();
+        assert_eq!(rendered, "/unix/tmp/foo.sock");
+        assert_eq!(rendered.parse::<S<Unix>>().unwrap(), addr);
+    }
+
+    #[test]
+    fn unix_missing_path_errors() {
+        assert!("/unix".parse::<S<Unix>>().is_err());
+    }
+
+    #[test]
+    fn dns_round_trips() {
+        let addr = S(Dns::new("example.com".to_owned()));
+        let rendered = addr.to_string();
+        assert_eq!(rendered, "/dns/example.com");
+        assert_eq!(rendered.parse::<S<Dns>>().unwrap(), addr);
+    }
+
+    #[test]
+    fn dns6_round_trips() {
+        let addr = S(Dns6::new("example.com".to_owned()));
+        let rendered = addr.to_string();
+        assert_eq!(rendered, "/dns6/example.com");
+        assert_eq!(rendered.parse::<S<Dns6>>().unwrap(), addr);
+    }
+
+    #[test]
+    fn dnsaddr_round_trips() {
+        let addr = S(Dnsaddr::new("example.com".to_owned()));
+        let rendered = addr.to_string();
+        assert_eq!(rendered, "/dnsaddr/example.com");
+        assert_eq!(rendered.parse::<S<Dnsaddr>>().unwrap(), addr);
+    }
+}
@@ -1,36 +1,57 @@
 use std::fmt;
+use std::hash::Hash;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 use mhash::MultiHash;
 
-/// The possible multiaddr segments.
+use codec::{Codable, DecodeError};
+use onion::{InvalidIdLength, OnionV2Address, OnionV3Address};
+use parse::{Parsable, ParseError};
+
+/// Implemented by every individual multiaddr protocol segment, e.g.
+/// [`IP4`](struct.IP4.html), [`Tcp`](struct.Tcp.html),
+/// [`Ipfs`](struct.Ipfs.html).
 ///
 /// # Examples
 ///
-/// This type can be converted from some of the standard library types, via
-/// `From`, e.g. from `Ipv4Addr`:
+/// Some segment types can be converted from standard library types, via
+/// `From`, e.g. `IP4` from `Ipv4Addr`:
 ///
 /// ```rust
 /// use std::net::Ipv4Addr;
-/// use maddr::Segment;
+/// use maddr::IP4;
 ///
 /// let addr = Ipv4Addr::new(1, 2, 3, 4);
-/// let segment = addr.into();
+/// let segment: IP4 = addr.into();
 ///
-/// assert_eq!(Segment::IP4(addr), segment);
+/// assert_eq!(IP4::from(addr), segment);
 /// ```
 ///
-/// Look at the [implementations](#implementations) section below for more.
-pub trait Segment: Eq + PartialEq + Clone {
+/// Look at the [implementors](#implementors) section below for the full list
+/// of segment types.
+pub trait Segment: Eq + PartialEq + Clone + Sized + Hash + Ord + 'static {
     fn code() -> u64;
     fn name() -> &'static str;
     fn data<'a>(&'a self) -> Box<Iterator<Item=&'a fmt::Display> + 'a>;
+
+    /// Encode this segment's value (everything after the protocol code) into
+    /// its canonical binary form.
+    fn write_value(&self, out: &mut Vec<u8>);
+
+    /// Decode a segment's value from its canonical binary form, having
+    /// already read and matched the protocol code.
+    fn read_value(input: &mut &[u8]) -> Result<Self, DecodeError>;
+
+    /// Parse this segment's argument components, having already read and
+    /// matched the protocol name, out of the human-readable `/proto/...`
+    /// form.
+    fn parse_args<'a, I: Iterator<Item=&'a str>>(components: &mut I) -> Result<Self, ParseError>;
 }
 
 macro_rules! segment {
     ($code:expr, $name:expr, { $(#[$doc:meta])* $ty:ident }) => {
         $(#[$doc])*
-        #[derive(Eq, PartialEq, Clone)]
+        #[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
         pub struct $ty;
         impl Segment for $ty {
             fn code() -> u64 { $code }
@@ -38,13 +59,24 @@ macro_rules! segment {
             fn data<'a>(&'a self) -> Box<Iterator<Item=&'a fmt::Display> + 'a> {
                 Box::new(None.into_iter())
             }
+
+            fn write_value(&self, _out: &mut Vec<u8>) {
+            }
+
+            fn read_value(_input: &mut &[u8]) -> Result<$ty, DecodeError> {
+                Ok($ty)
+            }
+
+            fn parse_args<'a, I: Iterator<Item=&'a str>>(_components: &mut I) -> Result<$ty, ParseError> {
+                Ok($ty)
+            }
         }
     };
 
     ($code:expr, $name:expr, { $(#[$doc:meta])* $ty:ident { $($arg_name:ident : $arg_ty:path),* } }) => {
         $(#[$doc])*
-        #[derive(Eq, PartialEq, Clone)]
-        pub struct $ty { $( $arg_name: $arg_ty),* }
+        #[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+        pub struct $ty { $( pub(crate) $arg_name: $arg_ty),* }
         impl Segment for $ty {
             fn code() -> u64 { $code }
             fn name() -> &'static str { $name }
@@ -52,6 +84,35 @@ macro_rules! segment {
                 let v: Vec<&fmt::Display> = vec![$(&self.$arg_name),*];
                 Box::new(v.into_iter())
             }
+
+            fn write_value(&self, out: &mut Vec<u8>) {
+                $( Codable::write(&self.$arg_name, out); )*
+            }
+
+            fn read_value(input: &mut &[u8]) -> Result<$ty, DecodeError> {
+                Ok($ty { $( $arg_name: try!(Codable::read(input)) ),* })
+            }
+
+            fn parse_args<'a, I: Iterator<Item=&'a str>>(components: &mut I) -> Result<$ty, ParseError> {
+                Ok($ty { $( $arg_name: try!(Parsable::parse(
+                    $name,
+                    try!(components.next().ok_or(ParseError::MissingArgument($name)))
+                )) ),* })
+            }
+        }
+    };
+}
+
+/// Add a public constructor to a segment type generated by `segment!`, for
+/// types that don't already have one of their own (e.g. `Onion`'s validating
+/// constructor, or `IP4`'s `From<Ipv4Addr>` impl).
+macro_rules! segment_ctor {
+    ($ty:ident { $($arg_name:ident : $arg_ty:path),* }) => {
+        impl $ty {
+            /// Construct this segment directly from its argument(s).
+            pub fn new($($arg_name: $arg_ty),*) -> $ty {
+                $ty { $( $arg_name ),* }
+            }
         }
     };
 }
@@ -61,6 +122,35 @@ segment!(33, "dccp", {
     /// The argument is the port number.
     Dccp { port: u16 }
 });
+segment_ctor!(Dccp { port: u16 });
+
+segment!(53, "dns", {
+    /// A DNS address (either version), an internet layer protocol. The
+    /// argument is the hostname.
+    Dns { host: String }
+});
+segment_ctor!(Dns { host: String });
+
+segment!(54, "dns4", {
+    /// A DNS version 4 address, an internet layer protocol. The argument
+    /// is the hostname.
+    Dns4 { host: String }
+});
+segment_ctor!(Dns4 { host: String });
+
+segment!(55, "dns6", {
+    /// A DNS version 6 address, an internet layer protocol. The argument
+    /// is the hostname.
+    Dns6 { host: String }
+});
+segment_ctor!(Dns6 { host: String });
+
+segment!(56, "dnsaddr", {
+    /// A DNS address resolved via a `TXT` record lookup, an internet layer
+    /// protocol. The argument is the hostname.
+    Dnsaddr { host: String }
+});
+segment_ctor!(Dnsaddr { host: String });
 
 segment!(480, "http", {
     /// Hypertext Transfer Protocol, an application layer protocol.
@@ -87,32 +177,111 @@ segment!(421, "ipfs", {
     /// The InterPlanetary File System, an application layer protocol.
     Ipfs { hash: MultiHash }
 });
+segment_ctor!(Ipfs { hash: MultiHash });
+
+segment!(444, "onion", {
+    /// A Tor version 2 hidden service, an application layer protocol. The
+    /// argument is the 80-bit service id and port, e.g.
+    /// `timaq4ygg2iegci7:80`.
+    Onion { addr: OnionV2Address }
+});
+
+segment!(445, "onion3", {
+    /// A Tor version 3 hidden service, an application layer protocol. The
+    /// argument is the 56-character service id and port.
+    Onion3 { addr: OnionV3Address }
+});
+
+segment!(290, "p2p-circuit", {
+    /// A libp2p relay hop, an application layer protocol.
+    P2pCircuit
+});
+
+segment!(460, "quic", {
+    /// The QUIC transport protocol, a transport layer protocol.
+    Quic
+});
 
 segment!(132, "sctp", {
     /// Stream Control Transmission Protocol, a transport layer protocol.
     Sctp { port: u16 }
 });
+segment_ctor!(Sctp { port: u16 });
 
 segment!(6, "tcp", {
     /// Transmission Control Protocol, a transport layer protocol.
     Tcp { port: u16 }
 });
+segment_ctor!(Tcp { port: u16 });
 
 segment!(17, "udp", {
     /// User Datagram Protocol, a transport layer protocol.
     Udp { port: u16 }
 });
+segment_ctor!(Udp { port: u16 });
 
 segment!(301, "udt", {
     /// UDP-based Data Transfer Protocol, an application layer protocol.
     Udt
 });
 
+/// A Unix domain socket, a transport layer protocol. The argument is the
+/// socket's path.
+///
+/// Unlike the other segments taking a string argument, the path is not
+/// split on `/`: everything after `/unix/` is taken verbatim as the path,
+/// so that it may itself contain slashes.
+#[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+pub struct Unix { path: String }
+
+impl Unix {
+    /// Construct a `unix` segment from an absolute path to a socket, e.g.
+    /// `/tmp/foo.sock`.
+    pub fn new(path: String) -> Unix {
+        Unix { path: path.trim_start_matches('/').to_owned() }
+    }
+}
+
+impl Segment for Unix {
+    fn code() -> u64 { 400 }
+    fn name() -> &'static str { "unix" }
+    fn data<'a>(&'a self) -> Box<Iterator<Item=&'a fmt::Display> + 'a> {
+        Box::new(Some(&self.path as &fmt::Display).into_iter())
+    }
+
+    fn write_value(&self, out: &mut Vec<u8>) {
+        Codable::write(&self.path, out);
+    }
+
+    fn read_value(input: &mut &[u8]) -> Result<Unix, DecodeError> {
+        Ok(Unix { path: try!(Codable::read(input)) })
+    }
+
+    fn parse_args<'a, I: Iterator<Item=&'a str>>(components: &mut I) -> Result<Unix, ParseError> {
+        let path = components.collect::<Vec<_>>().join("/");
+        if path.is_empty() {
+            return Err(ParseError::MissingArgument("unix"));
+        }
+        Ok(Unix { path: path })
+    }
+}
+
 segment!(302, "utp", {
     /// Micro Transport Protocol, an application? layer protocol.
     Utp
 });
 
+segment!(477, "ws", {
+    /// WebSocket, an application layer protocol.
+    Ws
+});
+
+segment!(478, "wss", {
+    /// WebSocket layered on top of Transport Layer Security, an
+    /// application layer protocol.
+    Wss
+});
+
 impl From<Ipv4Addr> for IP4 {
     fn from(ip: Ipv4Addr) -> IP4 {
         IP4 { ip }
@@ -125,37 +294,45 @@ impl From<Ipv6Addr> for IP6 {
     }
 }
 
+impl Onion {
+    /// Construct an `onion` segment from a 10-byte (80-bit) service id and
+    /// a port. Errors if `id` is not exactly 10 bytes.
+    pub fn new(id: Vec<u8>, port: u16) -> Result<Onion, InvalidIdLength> {
+        if id.len() != 10 {
+            return Err(InvalidIdLength { expected: 10, found: id.len() });
+        }
+        Ok(Onion { addr: OnionV2Address { id, port } })
+    }
+}
+
+impl Onion3 {
+    /// Construct an `onion3` segment from a 35-byte service id and a port.
+    /// Errors if `id` is not exactly 35 bytes.
+    pub fn new(id: Vec<u8>, port: u16) -> Result<Onion3, InvalidIdLength> {
+        if id.len() != 35 {
+            return Err(InvalidIdLength { expected: 35, found: id.len() });
+        }
+        Ok(Onion3 { addr: OnionV3Address { id, port } })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::net::{Ipv4Addr, Ipv6Addr};
 
-    use Segment;
+    use segment::{IP4, IP6};
 
     #[test]
     fn from_ip4() {
-        assert_eq!(
-            Segment::IP4(Ipv4Addr::new(1, 2, 3, 4)),
-            Ipv4Addr::new(1, 2, 3, 4).into());
+        let addr = Ipv4Addr::new(1, 2, 3, 4);
+        let segment: IP4 = addr.into();
+        assert_eq!(IP4::from(addr), segment);
     }
 
     #[test]
     fn from_ip6() {
-        assert_eq!(
-            Segment::IP6(Ipv6Addr::new(0x2a02, 0x6b8, 0, 0, 0, 0, 0x11, 0x11)),
-            Ipv6Addr::new(0x2a02, 0x6b8, 0, 0, 0, 0, 0x11, 0x11).into());
-    }
-
-    #[test]
-    fn from_ip_ip4() {
-        assert_eq!(
-            Segment::IP4(Ipv4Addr::new(1, 2, 3, 4)),
-            IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)).into());
-    }
-
-    #[test]
-    fn from_ip_ip6() {
-        assert_eq!(
-            Segment::IP6(Ipv6Addr::new(0x2a02, 0x6b8, 0, 0, 0, 0, 0x11, 0x11)),
-            IpAddr::V6(Ipv6Addr::new(0x2a02, 0x6b8, 0, 0, 0, 0, 0x11, 0x11)).into());
+        let addr = Ipv6Addr::new(0x2a02, 0x6b8, 0, 0, 0, 0, 0x11, 0x11);
+        let segment: IP6 = addr.into();
+        assert_eq!(IP6::from(addr), segment);
     }
 }